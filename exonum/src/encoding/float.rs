@@ -12,17 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::mem;
 use std::error::Error;
+use std::mem;
 
 use byteorder::{ByteOrder, LittleEndian};
-use serde_json::value::{Value, Number};
+use serde_json::value::{Number, Value};
 
-use super::Result as EncodingResult;
 use super::Error as EncodingError;
-use encoding::{CheckedOffset, Field, Offset};
-use encoding::serialize::WriteBufferWrapper;
+use super::Result as EncodingResult;
 use encoding::serialize::json::ExonumJson;
+use encoding::serialize::WriteBufferWrapper;
+use encoding::{CheckedOffset, Field, Offset};
 
 /// Wrapper for the `f32` type that restricts non-finite (NaN and Infinity) values.
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
@@ -150,17 +150,35 @@ impl F64 {
     }
 }
 
+/// Byte order `Field::read`/`write` use for `F32`/`F64`. `Field`'s methods
+/// take no parameters we could thread a runtime choice through, so the order
+/// is fixed at compile time via this alias; flip it with the
+/// `big_endian_floats` feature for interop with big-endian readers/tools.
+/// Defaults to `LittleEndian` to match every other `Field` impl in this crate.
+///
+/// Unlike `views::metadata::Endianness`, this is a compile-time switch only:
+/// it does not read the `IndexesPool` byte-order marker, so a database
+/// persisted as `Endianness::Big` is still read/written as little-endian
+/// here unless the binary itself is rebuilt with `big_endian_floats`. `Field`
+/// would need a signature change to carry that runtime choice in.
+#[cfg(not(feature = "big_endian_floats"))]
+type ConfiguredByteOrder = LittleEndian;
+#[cfg(feature = "big_endian_floats")]
+type ConfiguredByteOrder = byteorder::BigEndian;
+
 impl<'a> Field<'a> for F32 {
     fn field_size() -> Offset {
         mem::size_of::<Self>() as Offset
     }
 
     unsafe fn read(buffer: &'a [u8], from: Offset, to: Offset) -> Self {
-        Self::new(LittleEndian::read_f32(&buffer[from as usize..to as usize]))
+        Self::new(ConfiguredByteOrder::read_f32(
+            &buffer[from as usize..to as usize],
+        ))
     }
 
     fn write(&self, buffer: &mut Vec<u8>, from: Offset, to: Offset) {
-        LittleEndian::write_f32(&mut buffer[from as usize..to as usize], self.get());
+        ConfiguredByteOrder::write_f32(&mut buffer[from as usize..to as usize], self.get());
     }
 
     fn check(
@@ -174,7 +192,7 @@ impl<'a> Field<'a> for F32 {
         let from = from.unchecked_offset();
         let to = to.unchecked_offset();
 
-        let value = LittleEndian::read_f32(&buffer[from as usize..to as usize]);
+        let value = ConfiguredByteOrder::read_f32(&buffer[from as usize..to as usize]);
         match Self::try_from(value) {
             Some(_) => Ok(latest_segment),
             None => Err(EncodingError::UnsupportedFloat {
@@ -191,11 +209,13 @@ impl<'a> Field<'a> for F64 {
     }
 
     unsafe fn read(buffer: &'a [u8], from: Offset, to: Offset) -> Self {
-        Self::new(LittleEndian::read_f64(&buffer[from as usize..to as usize]))
+        Self::new(ConfiguredByteOrder::read_f64(
+            &buffer[from as usize..to as usize],
+        ))
     }
 
     fn write(&self, buffer: &mut Vec<u8>, from: Offset, to: Offset) {
-        LittleEndian::write_f64(&mut buffer[from as usize..to as usize], self.get());
+        ConfiguredByteOrder::write_f64(&mut buffer[from as usize..to as usize], self.get());
     }
 
     fn check(
@@ -209,7 +229,7 @@ impl<'a> Field<'a> for F64 {
         let from = from.unchecked_offset();
         let to = to.unchecked_offset();
 
-        let value = LittleEndian::read_f64(&buffer[from as usize..to as usize]);
+        let value = ConfiguredByteOrder::read_f64(&buffer[from as usize..to as usize]);
         match Self::try_from(value) {
             Some(_) => Ok(latest_segment),
             None => Err(EncodingError::UnsupportedFloat {
@@ -227,21 +247,30 @@ impl ExonumJson for F32 {
         from: Offset,
         to: Offset,
     ) -> Result<(), Box<Error>> {
+        if cfg!(feature = "float_serialize_hex") {
+            if let Some(text) = value.as_str() {
+                let parsed = parse_hex_f32(text).ok_or("Can't parse hex float")?;
+                let value = Self::try_from(parsed).ok_or("hex float is not finite")?;
+                buffer.write(from, to, value);
+                return Ok(());
+            }
+        }
         let number = value.as_f64().ok_or("Can't cast json as float")?;
         buffer.write(from, to, Self::new(number as f32));
         Ok(())
     }
 
     fn serialize_field(&self) -> Result<Value, Box<Error>> {
+        if cfg!(feature = "float_serialize_hex") {
+            return Ok(Value::String(to_hex_f32(self.get())));
+        }
         Ok(Value::Number(
-            Number::from_f64(f64::from(self.get())).ok_or(
-                "Can't cast float as json",
-            )?,
+            Number::from_f64(f64::from(self.get())).ok_or("Can't cast float as json")?,
         ))
     }
 }
 
-#[cfg(feature="float_serialize")]
+#[cfg(feature = "float_serialize")]
 impl ExonumJson for F64 {
     fn deserialize_field<B: WriteBufferWrapper>(
         value: &Value,
@@ -249,14 +278,399 @@ impl ExonumJson for F64 {
         from: Offset,
         to: Offset,
     ) -> Result<(), Box<Error>> {
+        if cfg!(feature = "float_serialize_hex") {
+            if let Some(text) = value.as_str() {
+                let parsed = parse_hex_f64(text).ok_or("Can't parse hex float")?;
+                let value = Self::try_from(parsed).ok_or("hex float is not finite")?;
+                buffer.write(from, to, value);
+                return Ok(());
+            }
+        }
         let number = value.as_f64().ok_or("Can't cast json as float")?;
         buffer.write(from, to, Self::new(number));
         Ok(())
     }
 
     fn serialize_field(&self) -> Result<Value, Box<Error>> {
-        Ok(Value::Number(Number::from_f64(self.get()).ok_or(
-            "Can't cast float as json",
-        )?))
+        if cfg!(feature = "float_serialize_hex") {
+            return Ok(Value::String(to_hex_f64(self.get())));
+        }
+        Ok(Value::Number(
+            Number::from_f64(self.get()).ok_or("Can't cast float as json")?,
+        ))
+    }
+}
+
+/// Describes a field's JSON representation as a [JSON Schema][json-schema]
+/// document, so downstream tooling can validate transaction JSON (or
+/// generate bindings) without reimplementing `ExonumJson`'s rules by hand.
+///
+/// [json-schema]: https://json-schema.org/
+pub trait JsonSchema {
+    /// Returns the JSON Schema fragment describing this type's JSON
+    /// representation.
+    fn json_schema() -> Value;
+}
+
+impl JsonSchema for F32 {
+    fn json_schema() -> Value {
+        finite_number_schema()
+    }
+}
+
+impl JsonSchema for F64 {
+    fn json_schema() -> Value {
+        finite_number_schema()
+    }
+}
+
+/// Schema shared by `F32` and `F64`: a JSON number, excluding the non-finite
+/// values (`NaN`, `Infinity`) that `F32::try_from`/`F64::try_from` reject.
+///
+/// When the `float_serialize_hex` feature is enabled, `serialize_field`
+/// instead emits a C99 hex-float string; callers that turn on that feature
+/// should validate against `hex_float_schema()` rather than this one.
+fn finite_number_schema() -> Value {
+    let mut schema = serde_json::Map::new();
+    schema.insert("type".to_string(), Value::String("number".to_string()));
+    schema.insert(
+        "description".to_string(),
+        Value::String(
+            "A finite IEEE 754 floating-point number (NaN and Infinity are rejected).".to_string(),
+        ),
+    );
+    Value::Object(schema)
+}
+
+/// Schema for the hex-float JSON string produced when `float_serialize_hex`
+/// is enabled (see `to_hex_f32`/`to_hex_f64`).
+pub fn hex_float_schema() -> Value {
+    let mut schema = serde_json::Map::new();
+    schema.insert("type".to_string(), Value::String("string".to_string()));
+    schema.insert(
+        "pattern".to_string(),
+        Value::String(r"^-?0x[01]\.[0-9a-f]+p[+-][0-9]+$".to_string()),
+    );
+    schema.insert(
+        "description".to_string(),
+        Value::String(
+            "A C99 hexadecimal floating-point literal encoding the exact bit pattern \
+             of a finite float."
+                .to_string(),
+        ),
+    );
+    Value::Object(schema)
+}
+
+/// Assembles a JSON Schema `object` document for a message type from the
+/// schemas of its named fields, e.g.
+/// `message_schema(&[("amount", F64::json_schema())])`.
+pub fn message_schema(fields: &[(&str, Value)]) -> Value {
+    let mut properties = serde_json::Map::new();
+    let mut required = Vec::with_capacity(fields.len());
+    for (name, schema) in fields {
+        properties.insert((*name).to_string(), schema.clone());
+        required.push(Value::String((*name).to_string()));
+    }
+
+    let mut document = serde_json::Map::new();
+    document.insert("type".to_string(), Value::String("object".to_string()));
+    document.insert("properties".to_string(), Value::Object(properties));
+    document.insert("required".to_string(), Value::Array(required));
+    document.insert("additionalProperties".to_string(), Value::Bool(false));
+    Value::Object(document)
+}
+
+/// Encodes `value` as a C99 hexadecimal floating-point literal (e.g.
+/// `0x1.91eb86p+1`), preserving its exact bit pattern. Used by the
+/// `float_serialize_hex` JSON representation so transactions round-trip
+/// without the precision loss `f32 -> f64 -> shortest decimal` incurs.
+fn to_hex_f32(value: f32) -> String {
+    let bits = value.to_bits();
+    let sign = if bits >> 31 == 1 { "-" } else { "" };
+    let biased_exponent = (bits >> 23) & 0xFF;
+    let mantissa = bits & 0x007f_ffff;
+    let (leading_digit, exponent) = if biased_exponent == 0 {
+        (0, 1 - 127)
+    } else {
+        (1, biased_exponent as i32 - 127)
+    };
+    // Shift the 23-bit mantissa into a 24-bit (6 hex nibble) field.
+    format!(
+        "{}0x{}.{:06x}p{}{}",
+        sign,
+        leading_digit,
+        mantissa << 1,
+        if exponent < 0 { "-" } else { "+" },
+        exponent.abs()
+    )
+}
+
+/// Encodes `value` as a C99 hexadecimal floating-point literal with a
+/// 13-nibble (52-bit) significand, preserving the exact `f64` bit pattern.
+fn to_hex_f64(value: f64) -> String {
+    let bits = value.to_bits();
+    let sign = if bits >> 63 == 1 { "-" } else { "" };
+    let biased_exponent = (bits >> 52) & 0x7ff;
+    let mantissa = bits & 0x000f_ffff_ffff_ffff;
+    let (leading_digit, exponent) = if biased_exponent == 0 {
+        (0, 1 - 1023)
+    } else {
+        (1, biased_exponent as i64 - 1023)
+    };
+    format!(
+        "{}0x{}.{:013x}p{}{}",
+        sign,
+        leading_digit,
+        mantissa,
+        if exponent < 0 { "-" } else { "+" },
+        exponent.abs()
+    )
+}
+
+/// Bias and maximum valid biased value of `f32`'s 8-bit exponent field.
+/// Biased `0` is reserved for subnormals/zero and `255` for Infinity/NaN, so
+/// a normal (`leading_digit == 1`) value must land in `1..=254`.
+const F32_EXPONENT_BIAS: i64 = 127;
+const F32_MAX_BIASED_EXPONENT: i64 = 254;
+
+/// Bias and maximum valid biased value of `f64`'s 11-bit exponent field
+/// (biased `0` and `2047` are reserved the same way as for `f32`).
+const F64_EXPONENT_BIAS: i64 = 1023;
+const F64_MAX_BIASED_EXPONENT: i64 = 2046;
+
+/// Parses a hex float produced by `to_hex_f32`. Returns `None` on malformed
+/// input rather than erroring, mirroring the fallback to `as_f64` this is
+/// layered on top of. Also returns `None` if the exponent doesn't fit the
+/// 8-bit biased exponent field: left unchecked, an in-range-looking
+/// exponent like `+300` would bleed into the sign bit instead of erroring,
+/// and an exponent near `i32::MAX` would overflow the biasing addition.
+fn parse_hex_f32(text: &str) -> Option<f32> {
+    let (negative, rest) = strip_sign(text);
+    let rest = rest.strip_prefix("0x")?;
+    let p_pos = rest.find('p')?;
+    let (mantissa_part, exponent_part) = (&rest[..p_pos], &rest[p_pos + 1..]);
+    let dot_pos = mantissa_part.find('.')?;
+    let (leading_part, frac_part) = (&mantissa_part[..dot_pos], &mantissa_part[dot_pos + 1..]);
+
+    let leading_digit: u32 = leading_part.parse().ok()?;
+    if frac_part.len() != 6 {
+        return None;
+    }
+    let nibbles = u32::from_str_radix(frac_part, 16).ok()?;
+    let mantissa = nibbles >> 1;
+    let exponent: i32 = exponent_part.parse().ok()?;
+
+    let biased_exponent = if leading_digit == 0 {
+        0
+    } else if leading_digit == 1 {
+        let biased = i64::from(exponent).checked_add(F32_EXPONENT_BIAS)?;
+        if biased < 1 || biased > F32_MAX_BIASED_EXPONENT {
+            return None;
+        }
+        biased as u32
+    } else {
+        return None;
+    };
+
+    let bits = ((negative as u32) << 31) | (biased_exponent << 23) | mantissa;
+    Some(f32::from_bits(bits))
+}
+
+/// Parses a hex float produced by `to_hex_f64`. See `parse_hex_f32` for why
+/// the exponent is range-checked before biasing.
+fn parse_hex_f64(text: &str) -> Option<f64> {
+    let (negative, rest) = strip_sign(text);
+    let rest = rest.strip_prefix("0x")?;
+    let p_pos = rest.find('p')?;
+    let (mantissa_part, exponent_part) = (&rest[..p_pos], &rest[p_pos + 1..]);
+    let dot_pos = mantissa_part.find('.')?;
+    let (leading_part, frac_part) = (&mantissa_part[..dot_pos], &mantissa_part[dot_pos + 1..]);
+
+    let leading_digit: u32 = leading_part.parse().ok()?;
+    if frac_part.len() != 13 {
+        return None;
+    }
+    let mantissa = u64::from_str_radix(frac_part, 16).ok()?;
+    let exponent: i64 = exponent_part.parse().ok()?;
+
+    let biased_exponent = if leading_digit == 0 {
+        0
+    } else if leading_digit == 1 {
+        let biased = exponent.checked_add(F64_EXPONENT_BIAS)?;
+        if biased < 1 || biased > F64_MAX_BIASED_EXPONENT {
+            return None;
+        }
+        biased as u64
+    } else {
+        return None;
+    };
+
+    let bits = ((negative as u64) << 63) | (biased_exponent << 52) | mantissa;
+    Some(f64::from_bits(bits))
+}
+
+fn strip_sign(text: &str) -> (bool, &str) {
+    match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+    use super::{
+        message_schema, parse_hex_f32, parse_hex_f64, to_hex_f32, to_hex_f64, Field, JsonSchema,
+        F32, F64,
+    };
+
+    #[test]
+    fn test_hex_f32_round_trip() {
+        let values: &[f32] = &[
+            0.0,
+            -0.0,
+            1.0,
+            -1.0,
+            3.14159,
+            f32::MIN_POSITIVE,
+            f32::MIN_POSITIVE / 2.0, // subnormal
+            f32::MAX,
+            f32::MIN,
+        ];
+        for &value in values {
+            let hex = to_hex_f32(value);
+            let parsed = parse_hex_f32(&hex).unwrap();
+            assert_eq!(
+                value.to_bits(),
+                parsed.to_bits(),
+                "round trip failed for {} via {}",
+                value,
+                hex
+            );
+        }
+    }
+
+    #[test]
+    fn test_hex_f64_round_trip() {
+        let values: &[f64] = &[
+            0.0,
+            -0.0,
+            1.0,
+            -1.0,
+            2.0_f64.sqrt(),
+            f64::MIN_POSITIVE,
+            f64::MIN_POSITIVE / 2.0, // subnormal
+            f64::MAX,
+            f64::MIN,
+        ];
+        for &value in values {
+            let hex = to_hex_f64(value);
+            let parsed = parse_hex_f64(&hex).unwrap();
+            assert_eq!(
+                value.to_bits(),
+                parsed.to_bits(),
+                "round trip failed for {} via {}",
+                value,
+                hex
+            );
+        }
+    }
+
+    #[test]
+    fn test_hex_float_rejects_non_finite() {
+        assert!(F32::try_from(f32::NAN).is_none());
+        assert!(F64::try_from(f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_parse_hex_f32_overflowing_exponent_is_rejected_not_panicking() {
+        // A syntactically valid hex float whose exponent overflows the f32
+        // exponent field must be rejected by the range check in
+        // `parse_hex_f32` itself, rather than producing an infinity (or
+        // worse, silently bleeding into the sign/magnitude bits).
+        assert!(parse_hex_f32("0x1.000000p+384").is_none());
+    }
+
+    #[test]
+    fn test_parse_hex_f64_overflowing_exponent_is_rejected_not_panicking() {
+        assert!(parse_hex_f64("0x1.0000000000000p+3072").is_none());
+    }
+
+    #[test]
+    fn test_parse_hex_f32_exponent_bleeding_into_sign_bit_is_rejected() {
+        // Exponents just past the valid range don't overflow to infinity;
+        // unchecked, they'd bleed into the sign bit and produce a finite
+        // but wrong value instead of an error.
+        for exponent in 129..=137 {
+            let text = format!("0x1.000000p+{}", exponent);
+            assert!(
+                parse_hex_f32(&text).is_none(),
+                "expected {} to be rejected",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_hex_float_exponent_near_i32_and_i64_max_does_not_panic() {
+        assert!(parse_hex_f32("0x1.000000p+2147483637").is_none());
+        assert!(parse_hex_f32(&format!("0x1.000000p+{}", i32::max_value())).is_none());
+        assert!(parse_hex_f64(&format!(
+            "0x1.0000000000000p+{}",
+            i64::max_value()
+        ))
+        .is_none());
+    }
+
+    #[test]
+    fn test_json_schema_is_a_finite_number_type() {
+        for schema in &[F32::json_schema(), F64::json_schema()] {
+            assert_eq!(schema["type"], "number");
+        }
+    }
+
+    #[test]
+    fn test_message_schema_lists_every_field_as_required() {
+        let schema =
+            message_schema(&[("amount", F64::json_schema()), ("rate", F32::json_schema())]);
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["properties"]["amount"]["type"], "number");
+        assert_eq!(schema["properties"]["rate"]["type"], "number");
+        assert_eq!(schema["required"], serde_json::json!(["amount", "rate"]));
+    }
+
+    #[test]
+    fn test_float_byte_order_read_write_round_trips() {
+        // `Field::read`/`write` are pinned to `ConfiguredByteOrder`, but the
+        // underlying read/write calls are generic over any `ByteOrder`; this
+        // confirms the abstraction round-trips regardless of which one is
+        // plugged in, independent of the `big_endian_floats` feature.
+        let mut le_buf = [0u8; 4];
+        LittleEndian::write_f32(&mut le_buf, 3.14159);
+        assert_eq!(LittleEndian::read_f32(&le_buf), 3.14159);
+
+        let mut be_buf = [0u8; 8];
+        BigEndian::write_f64(&mut be_buf, 2.71828);
+        assert_eq!(BigEndian::read_f64(&be_buf), 2.71828);
+        assert_ne!(be_buf[0], 0);
+    }
+
+    #[test]
+    fn test_field_byte_order_is_compile_time_only_and_does_not_follow_the_pool_marker() {
+        // `Field::write` always emits `ConfiguredByteOrder` bytes, which is
+        // `LittleEndian` unless the crate was built with `big_endian_floats`.
+        // There's no `IndexesPool` or `Endianness` marker in scope here --
+        // `Field`'s signature has no way to take one -- so this is pinned to
+        // little-endian regardless of what byte order a real database was
+        // persisted with.
+        let value = F32::new(3.14159);
+        let mut buffer = vec![0u8; F32::field_size() as usize];
+        value.write(&mut buffer, 0, F32::field_size());
+
+        let mut expected = [0u8; 4];
+        LittleEndian::write_f32(&mut expected, value.get());
+        assert_eq!(&buffer[..], &expected[..]);
     }
 }
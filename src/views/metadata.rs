@@ -14,7 +14,7 @@
 
 use std::{borrow::Cow, cell::Cell, mem};
 
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
 use enum_primitive_derive::Primitive;
 use failure::{self, ensure, format_err};
 use num_traits::FromPrimitive;
@@ -44,36 +44,136 @@ pub enum IndexType {
 
 /// TODO Add documentation. [ECR-2820]
 pub trait BinaryAttribute {
+    /// Tag identifying this attribute's concrete on-disk encoding. Written
+    /// alongside the `state` blob in `IndexMetadata` so a reader expecting a
+    /// different `BinaryAttribute` detects the mismatch and returns an error
+    /// instead of mis-parsing the blob; the blob is not skipped.
+    const TAG: u8;
     /// TODO Add documentation. [ECR-2820]
     fn size(&self) -> usize;
     /// TODO Add documentation. [ECR-2820]
-    fn write<W: std::io::Write>(&self, buffer: &mut W);
+    ///
+    /// Generic over `B` so multi-byte encodings (like the plain `u64` impl)
+    /// can be written in either byte order; single-byte/varint encodings
+    /// (like `Leb128<u64>`) simply ignore `B`.
+    fn write<B: ByteOrder, W: std::io::Write>(&self, buffer: &mut W);
     /// TODO Add documentation. [ECR-2820]
-    fn read<R: std::io::Read>(buffer: &mut R) -> Self;
+    fn read<B: ByteOrder, R: std::io::Read>(buffer: &mut R) -> Self;
+}
+
+/// Tag for the no-op `()` attribute.
+const UNIT_ATTRIBUTE_TAG: u8 = 0;
+/// Tag for the fixed-width `u64` attribute.
+const U64_ATTRIBUTE_TAG: u8 = 1;
+/// Tag for the `Leb128<u64>` attribute.
+const LEB128_U64_ATTRIBUTE_TAG: u8 = 2;
+
+/// Human-readable name for a known `BinaryAttribute::TAG`, used to make
+/// "unexpected state tag" errors legible; `None` for a tag this version
+/// doesn't know about.
+fn attribute_tag_name(tag: u8) -> Option<&'static str> {
+    match tag {
+        UNIT_ATTRIBUTE_TAG => Some("()"),
+        U64_ATTRIBUTE_TAG => Some("u64"),
+        LEB128_U64_ATTRIBUTE_TAG => Some("Leb128<u64>"),
+        _ => None,
+    }
 }
 
 /// No-op implementation.
 impl BinaryAttribute for () {
+    const TAG: u8 = UNIT_ATTRIBUTE_TAG;
+
     fn size(&self) -> usize {
         0
     }
 
-    fn write<W: std::io::Write>(&self, _buffer: &mut W) {}
+    fn write<B: ByteOrder, W: std::io::Write>(&self, _buffer: &mut W) {}
 
-    fn read<R: std::io::Read>(_buffer: &mut R) -> Self {}
+    fn read<B: ByteOrder, R: std::io::Read>(_buffer: &mut R) -> Self {}
 }
 
 impl BinaryAttribute for u64 {
+    const TAG: u8 = U64_ATTRIBUTE_TAG;
+
     fn size(&self) -> usize {
         mem::size_of_val(self)
     }
 
-    fn write<W: std::io::Write>(&self, buffer: &mut W) {
-        buffer.write_u64::<LittleEndian>(*self).unwrap()
+    fn write<B: ByteOrder, W: std::io::Write>(&self, buffer: &mut W) {
+        buffer.write_u64::<B>(*self).unwrap()
     }
 
-    fn read<R: std::io::Read>(buffer: &mut R) -> Self {
-        buffer.read_u64::<LittleEndian>().unwrap()
+    fn read<B: ByteOrder, R: std::io::Read>(buffer: &mut R) -> Self {
+        buffer.read_u64::<B>().unwrap()
+    }
+}
+
+/// Maximum number of bytes a LEB128-encoded `u64` can occupy; the 10th byte
+/// only ever contributes a single significant bit.
+const LEB128_MAX_BYTES: usize = 10;
+
+/// Writes `value` as an unsigned LEB128 varint: 7 payload bits per byte, with
+/// the high bit set on every byte except the last.
+fn write_leb128<W: std::io::Write>(buffer: &mut W, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            buffer.write_u8(byte).unwrap();
+            break;
+        } else {
+            buffer.write_u8(byte | 0x80).unwrap();
+        }
+    }
+}
+
+/// Reads an unsigned LEB128 varint written by `write_leb128`.
+fn read_leb128<R: std::io::Read>(buffer: &mut R) -> Result<u64, failure::Error> {
+    let mut value = 0u64;
+    for i in 0..LEB128_MAX_BYTES {
+        let byte = buffer.read_u8()?;
+        value |= u64::from(byte & 0x7F) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(format_err!(
+        "LEB128 varint is too long (more than {} bytes)",
+        LEB128_MAX_BYTES
+    ))
+}
+
+/// Number of bytes `write_leb128` would emit for `value`.
+fn leb128_len(mut value: u64) -> usize {
+    let mut len = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
+
+/// `BinaryAttribute` for index state counters that are almost always small:
+/// encodes `u64` as an unsigned LEB128 varint instead of 8 fixed bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Leb128<T>(pub T);
+
+impl BinaryAttribute for Leb128<u64> {
+    const TAG: u8 = LEB128_U64_ATTRIBUTE_TAG;
+
+    fn size(&self) -> usize {
+        leb128_len(self.0)
+    }
+
+    // LEB128 is a byte-at-a-time varint with no multi-byte words to order,
+    // so `B` is accepted (to satisfy the trait) but otherwise unused.
+    fn write<B: ByteOrder, W: std::io::Write>(&self, buffer: &mut W) {
+        write_leb128(buffer, self.0)
+    }
+
+    fn read<B: ByteOrder, R: std::io::Read>(buffer: &mut R) -> Self {
+        Leb128(read_leb128(buffer).expect("Malformed LEB128 varint"))
     }
 }
 
@@ -91,38 +191,113 @@ pub struct IndexMetadata<V> {
     state: V,
 }
 
-impl<V> BinaryValue for IndexMetadata<V>
+/// On-disk layout an `IndexMetadata` record is written in.
+///
+/// It is not guessed per record from a leading sentinel byte: a legacy
+/// fixed-width record's first byte is the low byte of `identifier`
+/// (attacker/workload-controlled), so any sentinel value would collide with
+/// some real identifier — e.g. every 256th index ever created in a
+/// database. `MetadataFormat` is instead decided once per database and
+/// persisted out-of-band in the `IndexesPool` header (see
+/// `METADATA_FORMAT_KEY`), the same way `Endianness` is, so it can never be
+/// confused with the data it describes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MetadataFormat {
+    /// Fixed-width layout used before LEB128 framing existed: `u64`
+    /// identifier, `u32` index type, `u32` state length, all in the
+    /// database's byte order, with `state` written directly (no TLV tag).
+    Legacy,
+    /// LEB128-framed layout: varint identifier, one-byte index type, then
+    /// TLV-framed `state` (see `BinaryAttribute::TAG`).
+    Leb128,
+}
+
+impl<V> IndexMetadata<V>
 where
     V: BinaryAttribute,
 {
-    fn to_bytes(&self) -> Vec<u8> {
+    /// Encodes this record in the given `format`, using byte order `B` for
+    /// any multi-byte fields. The LEB128-framed fields (identifier, state
+    /// length) don't actually depend on `B`, but the `state` blob's own
+    /// encoding might (e.g. the plain `u64` attribute); `B` is threaded
+    /// through so a database can pick byte order once (via its
+    /// `IndexesPool` marker) and have it apply consistently.
+    fn to_bytes_with_order<B: ByteOrder>(&self, format: MetadataFormat) -> Vec<u8> {
         let state_len = self.state.size();
-        let mut buf = Vec::with_capacity(
-            mem::size_of_val(&self.identifier)
-                + mem::size_of_val(&self.index_type)
-                + mem::size_of::<u32>()
-                + state_len,
-        );
 
-        buf.write_u64::<LittleEndian>(self.identifier).unwrap();
-        buf.write_u32::<LittleEndian>(self.index_type as u32)
-            .unwrap();
-        buf.write_u32::<LittleEndian>(state_len as u32).unwrap();
-        self.state.write(&mut buf);
-        buf
+        match format {
+            MetadataFormat::Legacy => {
+                let mut buf = Vec::with_capacity(
+                    mem::size_of_val(&self.identifier)
+                        + mem::size_of::<u32>()
+                        + mem::size_of::<u32>()
+                        + state_len,
+                );
+                buf.write_u64::<B>(self.identifier).unwrap();
+                buf.write_u32::<B>(self.index_type as u32).unwrap();
+                buf.write_u32::<B>(state_len as u32).unwrap();
+                self.state.write::<B, _>(&mut buf);
+                buf
+            }
+            MetadataFormat::Leb128 => {
+                let mut buf = Vec::with_capacity(
+                    leb128_len(self.identifier) + 1 + 1 + leb128_len(state_len as u64) + state_len,
+                );
+                write_leb128(&mut buf, self.identifier);
+                buf.write_u8(self.index_type as u32 as u8).unwrap();
+                // TLV framing for `state`: a tag identifying its concrete type,
+                // then a LEB128 length, then its bytes. A reader expecting a
+                // different `BinaryAttribute` gets a tag-mismatch error
+                // instead of mis-parsing the blob (see `attribute_tag_name`).
+                buf.write_u8(V::TAG).unwrap();
+                write_leb128(&mut buf, state_len as u64);
+                self.state.write::<B, _>(&mut buf);
+                buf
+            }
+        }
     }
 
-    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, failure::Error> {
+    /// Decodes a record written by `to_bytes_with_order::<B>(format)`. The
+    /// caller must supply the same `format` the record was written with —
+    /// see `MetadataFormat`'s doc comment for why that can't be recovered
+    /// from the bytes themselves.
+    fn from_bytes_with_order<B: ByteOrder>(
+        bytes: Cow<[u8]>,
+        format: MetadataFormat,
+    ) -> Result<Self, failure::Error> {
         let mut bytes = bytes.as_ref();
 
-        let identifier = bytes.read_u64::<LittleEndian>()?;
-        let index_type = bytes.read_u32::<LittleEndian>()?;
-        let state_len = bytes.read_u32::<LittleEndian>()? as usize;
+        let (identifier, index_type, state_tag, state_len) = match format {
+            MetadataFormat::Legacy => {
+                let identifier = bytes.read_u64::<B>()?;
+                let index_type = bytes.read_u32::<B>()?;
+                let state_len = bytes.read_u32::<B>()? as usize;
+                // Records in the legacy fixed-width format predate TLV framing
+                // and carry no state tag; treat them as already matching `V`.
+                (identifier, index_type, V::TAG, state_len)
+            }
+            MetadataFormat::Leb128 => {
+                let identifier = read_leb128(&mut bytes)?;
+                let index_type = u64::from(bytes.read_u8()?);
+                let state_tag = bytes.read_u8()?;
+                let state_len = read_leb128(&mut bytes)? as usize;
+                (identifier, index_type as u32, state_tag, state_len)
+            }
+        };
 
         ensure!(bytes.len() >= state_len, "Index state is too short");
 
+        if state_tag != V::TAG {
+            return Err(format_err!(
+                "Index state tag mismatch: expected {} ({}), found {}",
+                V::TAG,
+                attribute_tag_name(V::TAG).unwrap_or("unknown"),
+                attribute_tag_name(state_tag).unwrap_or("unknown"),
+            ));
+        }
+
         let mut state_bytes = &bytes[0..state_len];
-        let state = V::read(&mut state_bytes);
+        let state = V::read::<B, _>(&mut state_bytes);
 
         Ok(Self {
             identifier,
@@ -133,6 +308,23 @@ where
     }
 }
 
+impl<V> BinaryValue for IndexMetadata<V>
+where
+    V: BinaryAttribute,
+{
+    /// Encodes using little-endian byte order and the LEB128 layout. Callers
+    /// that want to respect a database's `IndexesPool` markers should go
+    /// through `to_bytes_with_order` instead (see `IndexesPool::byte_order`
+    /// and `IndexesPool::metadata_format`).
+    fn to_bytes(&self) -> Vec<u8> {
+        self.to_bytes_with_order::<LittleEndian>(MetadataFormat::Leb128)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, failure::Error> {
+        Self::from_bytes_with_order::<LittleEndian>(bytes, MetadataFormat::Leb128)
+    }
+}
+
 impl<V> IndexMetadata<V> {
     fn index_address(&self) -> IndexAddress {
         IndexAddress::new().append_bytes(&self.identifier)
@@ -162,21 +354,124 @@ where
     let index_name = index_address.index_name();
 
     let mut pool = IndexesPool::new(index_access);
-    let metadata = if let Some(metadata) = pool.index_metadata(&index_name) {
+    let byte_order = pool.byte_order();
+    let format = pool.metadata_format();
+    let metadata = if let Some(metadata) = pool.index_metadata(&index_name, byte_order, format) {
         assert_eq!(
             metadata.index_type, index_type,
             "Index type doesn't match specified"
         );
         metadata
     } else {
-        pool.create_index_metadata(&index_name, index_type)
+        pool.create_index_metadata(&index_name, index_type, byte_order, format)
     };
 
     let index_address = metadata.index_address();
-    let index_state = IndexState::new(index_access, index_name, metadata);
+    let index_state = IndexState::new(index_access, index_name, metadata, byte_order, format);
     (index_address, index_state)
 }
 
+/// Byte order a database's index metadata was written with. Persisted as a
+/// two-byte marker in the `IndexesPool` header, the same trick TIFF/EXIF
+/// readers use (`II`/`MM`) to self-describe their layout: a reader opens the
+/// database, reads the marker, and decodes its index metadata accordingly.
+///
+/// This governs `IndexMetadata` only. `F32`/`F64`'s `Field` impls
+/// (`exonum::encoding::float`) have their own, compile-time-only
+/// `ConfiguredByteOrder` switch (the `big_endian_floats` feature) that does
+/// *not* consult this marker -- see `ConfiguredByteOrder`'s doc comment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Endianness {
+    Little,
+    Big,
+}
+
+impl Default for Endianness {
+    /// New databases default to little-endian, matching the byte order this
+    /// module always used before the marker existed.
+    fn default() -> Self {
+        Endianness::Little
+    }
+}
+
+const LITTLE_ENDIAN_MARKER: &[u8; 2] = b"II";
+const BIG_ENDIAN_MARKER: &[u8; 2] = b"MM";
+
+impl BinaryValue for Endianness {
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Endianness::Little => LITTLE_ENDIAN_MARKER.to_vec(),
+            Endianness::Big => BIG_ENDIAN_MARKER.to_vec(),
+        }
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, failure::Error> {
+        match bytes.as_ref() {
+            b"II" => Ok(Endianness::Little),
+            b"MM" => Ok(Endianness::Big),
+            other => Err(format_err!("Unknown byte order marker: {:?}", other)),
+        }
+    }
+}
+
+/// Decodes `bytes` as `IndexMetadata<V>` written in the given byte `order`
+/// and `format`.
+fn decode_metadata<V: BinaryAttribute>(
+    order: Endianness,
+    format: MetadataFormat,
+    bytes: Vec<u8>,
+) -> Result<IndexMetadata<V>, failure::Error> {
+    match order {
+        Endianness::Little => {
+            IndexMetadata::from_bytes_with_order::<LittleEndian>(bytes.into(), format)
+        }
+        Endianness::Big => IndexMetadata::from_bytes_with_order::<BigEndian>(bytes.into(), format),
+    }
+}
+
+/// Encodes `metadata` in the given byte `order` and `format`.
+fn encode_metadata<V: BinaryAttribute>(
+    order: Endianness,
+    format: MetadataFormat,
+    metadata: &IndexMetadata<V>,
+) -> Vec<u8> {
+    match order {
+        Endianness::Little => metadata.to_bytes_with_order::<LittleEndian>(format),
+        Endianness::Big => metadata.to_bytes_with_order::<BigEndian>(format),
+    }
+}
+
+/// Marker byte values for `MetadataFormat`, persisted under
+/// `METADATA_FORMAT_KEY`.
+const LEGACY_FORMAT_MARKER: u8 = 0;
+const LEB128_FORMAT_MARKER: u8 = 1;
+
+impl BinaryValue for MetadataFormat {
+    fn to_bytes(&self) -> Vec<u8> {
+        vec![match self {
+            MetadataFormat::Legacy => LEGACY_FORMAT_MARKER,
+            MetadataFormat::Leb128 => LEB128_FORMAT_MARKER,
+        }]
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, failure::Error> {
+        match bytes.as_ref() {
+            [LEGACY_FORMAT_MARKER] => Ok(MetadataFormat::Legacy),
+            [LEB128_FORMAT_MARKER] => Ok(MetadataFormat::Leb128),
+            other => Err(format_err!("Unknown metadata format marker: {:?}", other)),
+        }
+    }
+}
+
+/// Key the `IndexesPool` header stores its metadata-format marker under.
+/// Distinct from `BYTE_ORDER_KEY` and the `()` key `len` uses.
+const METADATA_FORMAT_KEY: &[u8] = &[0xFD];
+
+/// Key the `IndexesPool` header stores its byte-order marker under. Chosen
+/// to be distinct from the `()` key `len` uses and from real `index_name`s,
+/// which are always built from an index's own (non-empty) name.
+const BYTE_ORDER_KEY: &[u8] = &[0xFE];
+
 /// TODO Add documentation. [ECR-2820]
 struct IndexesPool<T: IndexAccess>(View<T>);
 
@@ -194,17 +489,66 @@ impl<T: IndexAccess> IndexesPool<T> {
         self.0.put(&(), len)
     }
 
-    fn index_metadata<V>(&self, index_name: &[u8]) -> Option<IndexMetadata<V>>
+    /// Byte order this database's index metadata is encoded with. Reads the
+    /// `IndexesPool` header marker, defaulting new (markerless) databases to
+    /// little-endian.
+    fn byte_order(&self) -> Endianness {
+        self.0.get(BYTE_ORDER_KEY).unwrap_or_default()
+    }
+
+    /// Persists `order` as this database's byte-order marker, unless a marker
+    /// is already on disk (the marker is fixed for the lifetime of a
+    /// database, so repeating the same write on every index creation would
+    /// be wasted I/O).
+    fn persist_byte_order(&mut self, order: Endianness) {
+        if self.0.get::<Endianness>(BYTE_ORDER_KEY).is_none() {
+            self.0.put(BYTE_ORDER_KEY, order.to_bytes());
+        }
+    }
+
+    /// On-disk layout this database's index metadata is encoded with. Reads
+    /// the `IndexesPool` header marker; if it's missing, a database that
+    /// already has indexes predates the marker and is therefore in the
+    /// legacy fixed-width layout (the only one that ever existed before it),
+    /// while a database with no indexes yet is free to start out in the
+    /// more compact LEB128 layout.
+    fn metadata_format(&self) -> MetadataFormat {
+        self.0.get(METADATA_FORMAT_KEY).unwrap_or_else(|| {
+            if self.len() == 0 {
+                MetadataFormat::Leb128
+            } else {
+                MetadataFormat::Legacy
+            }
+        })
+    }
+
+    /// Persists `format` as this database's metadata-format marker, unless a
+    /// marker is already on disk (see `persist_byte_order`).
+    fn persist_metadata_format(&mut self, format: MetadataFormat) {
+        if self.0.get::<MetadataFormat>(METADATA_FORMAT_KEY).is_none() {
+            self.0.put(METADATA_FORMAT_KEY, format.to_bytes());
+        }
+    }
+
+    fn index_metadata<V>(
+        &self,
+        index_name: &[u8],
+        byte_order: Endianness,
+        format: MetadataFormat,
+    ) -> Option<IndexMetadata<V>>
     where
         V: BinaryAttribute + Default + Copy,
     {
-        self.0.get(index_name)
+        let bytes: Vec<u8> = self.0.get(index_name)?;
+        Some(decode_metadata(byte_order, format, bytes).expect("Malformed index metadata"))
     }
 
     fn create_index_metadata<V>(
         &mut self,
         index_name: &[u8],
         index_type: IndexType,
+        byte_order: Endianness,
+        format: MetadataFormat,
     ) -> IndexMetadata<V>
     where
         V: BinaryAttribute + Default + Copy,
@@ -217,8 +561,11 @@ impl<T: IndexAccess> IndexesPool<T> {
             state: V::default(),
         };
 
-        self.0.put(index_name, metadata.to_bytes());
+        self.0
+            .put(index_name, encode_metadata(byte_order, format, &metadata));
         self.set_len(len + 1);
+        self.persist_byte_order(byte_order);
+        self.persist_metadata_format(format);
         metadata
     }
 }
@@ -232,6 +579,8 @@ where
     index_access: T,
     index_name: Vec<u8>,
     cache: Cell<IndexMetadata<V>>,
+    byte_order: Endianness,
+    format: MetadataFormat,
 }
 
 impl<T, V> IndexState<T, V>
@@ -239,11 +588,19 @@ where
     V: BinaryAttribute + Default + Copy,
     T: IndexAccess,
 {
-    fn new(index_access: T, index_name: Vec<u8>, metadata: IndexMetadata<V>) -> Self {
+    fn new(
+        index_access: T,
+        index_name: Vec<u8>,
+        metadata: IndexMetadata<V>,
+        byte_order: Endianness,
+        format: MetadataFormat,
+    ) -> Self {
         Self {
             index_access,
             index_name,
             cache: Cell::new(metadata),
+            byte_order,
+            format,
         }
     }
 
@@ -256,8 +613,10 @@ where
     pub fn set(&mut self, state: V) {
         let mut cache = self.cache.get_mut();
         cache.state = state;
-        View::new(self.index_access, IndexAddress::from(INDEXES_POOL_NAME))
-            .put(&self.index_name, cache.to_bytes());
+        View::new(self.index_access, IndexAddress::from(INDEXES_POOL_NAME)).put(
+            &self.index_name,
+            encode_metadata(self.byte_order, self.format, cache),
+        );
     }
 }
 
@@ -275,20 +634,22 @@ where
 mod tests {
     use std::io::Cursor;
 
+    use byteorder::{BigEndian, LittleEndian, WriteBytesExt};
+
     use crate::BinaryValue;
 
-    use super::{BinaryAttribute, IndexMetadata, IndexType};
+    use super::{BinaryAttribute, Endianness, IndexMetadata, IndexType, Leb128, MetadataFormat};
 
     #[test]
     fn test_binary_attribute_read_write() {
         let mut buf = Vec::new();
-        11_u64.write(&mut buf);
-        12_u64.write(&mut buf);
+        11_u64.write::<LittleEndian, _>(&mut buf);
+        12_u64.write::<LittleEndian, _>(&mut buf);
         assert_eq!(buf.len(), 16);
 
         let mut reader = Cursor::new(buf);
-        let a = u64::read(&mut reader);
-        let b = u64::read(&mut reader);
+        let a = u64::read::<LittleEndian, _>(&mut reader);
+        let b = u64::read::<LittleEndian, _>(&mut reader);
         assert_eq!(a, 11);
         assert_eq!(b, 12);
     }
@@ -304,4 +665,175 @@ mod tests {
         let bytes = metadata.to_bytes();
         assert_eq!(IndexMetadata::from_bytes(bytes.into()).unwrap(), metadata);
     }
+
+    #[test]
+    fn test_leb128_round_trip() {
+        for &value in &[
+            0_u64,
+            1,
+            127,
+            128,
+            16_383,
+            16_384,
+            1 << 34,
+            u64::max_value(),
+        ] {
+            let mut buf = Vec::new();
+            Leb128(value).write::<LittleEndian, _>(&mut buf);
+            let mut reader = buf.as_slice();
+            assert_eq!(Leb128::<u64>::read::<LittleEndian, _>(&mut reader).0, value);
+        }
+    }
+
+    #[test]
+    fn test_leb128_small_values_are_compact() {
+        let mut buf = Vec::new();
+        Leb128(3_u64).write::<LittleEndian, _>(&mut buf);
+        assert_eq!(buf, vec![3]);
+    }
+
+    #[test]
+    fn test_index_metadata_binary_value_with_leb128_state() {
+        let metadata = IndexMetadata {
+            identifier: 300,
+            index_type: IndexType::ProofMap,
+            state: Leb128(16_384_u64),
+        };
+
+        let bytes = metadata.to_bytes();
+        assert_eq!(IndexMetadata::from_bytes(bytes.into()).unwrap(), metadata);
+    }
+
+    #[test]
+    fn test_index_metadata_reads_legacy_fixed_width_format() {
+        let mut buf = Vec::new();
+        buf.write_u64::<LittleEndian>(12).unwrap();
+        buf.write_u32::<LittleEndian>(IndexType::ProofList as u32)
+            .unwrap();
+        buf.write_u32::<LittleEndian>(8).unwrap();
+        16_u64.write::<LittleEndian, _>(&mut buf);
+
+        let metadata =
+            IndexMetadata::<u64>::from_bytes_with_order::<LittleEndian>(
+                buf.into(),
+                MetadataFormat::Legacy,
+            )
+            .unwrap();
+        assert_eq!(metadata.identifier, 12);
+        assert_eq!(metadata.index_type, IndexType::ProofList);
+        assert_eq!(metadata.state, 16);
+    }
+
+    #[test]
+    fn test_index_metadata_does_not_misparse_legacy_record_as_leb128() {
+        // Regression test: a legacy record whose low identifier byte happens
+        // to equal a plausible sentinel value must still only be decodable
+        // as `Legacy` — the format is never guessed from the bytes.
+        let mut buf = Vec::new();
+        buf.write_u64::<LittleEndian>(255).unwrap();
+        buf.write_u32::<LittleEndian>(IndexType::ProofList as u32)
+            .unwrap();
+        buf.write_u32::<LittleEndian>(8).unwrap();
+        16_u64.write::<LittleEndian, _>(&mut buf);
+
+        let metadata = IndexMetadata::<u64>::from_bytes_with_order::<LittleEndian>(
+            buf.into(),
+            MetadataFormat::Legacy,
+        )
+        .unwrap();
+        assert_eq!(metadata.identifier, 255);
+        assert_eq!(metadata.index_type, IndexType::ProofList);
+        assert_eq!(metadata.state, 16);
+    }
+
+    #[test]
+    fn test_index_metadata_rejects_mismatched_state_tag() {
+        let metadata = IndexMetadata {
+            identifier: 1,
+            index_type: IndexType::Entry,
+            state: Leb128(7_u64),
+        };
+        let bytes = metadata.to_bytes();
+
+        // Reading the same bytes back as a plain `u64` state must fail with
+        // a typed error instead of mis-parsing the `Leb128<u64>` encoding.
+        let error = IndexMetadata::<u64>::from_bytes(bytes.into()).unwrap_err();
+        assert!(error.to_string().contains("state tag mismatch"));
+    }
+
+    #[test]
+    fn test_index_metadata_round_trips_in_either_byte_order() {
+        let metadata = IndexMetadata {
+            identifier: 42,
+            index_type: IndexType::KeySet,
+            state: 7_u64,
+        };
+
+        let le_bytes = metadata.to_bytes_with_order::<LittleEndian>(MetadataFormat::Leb128);
+        assert_eq!(
+            IndexMetadata::from_bytes_with_order::<LittleEndian>(
+                le_bytes.into(),
+                MetadataFormat::Leb128
+            )
+            .unwrap(),
+            metadata
+        );
+
+        let be_bytes = metadata.to_bytes_with_order::<BigEndian>(MetadataFormat::Leb128);
+        assert_eq!(
+            IndexMetadata::from_bytes_with_order::<BigEndian>(
+                be_bytes.into(),
+                MetadataFormat::Leb128
+            )
+            .unwrap(),
+            metadata
+        );
+    }
+
+    #[test]
+    fn test_legacy_fixed_width_format_respects_byte_order() {
+        let mut buf = Vec::new();
+        buf.write_u64::<BigEndian>(12).unwrap();
+        buf.write_u32::<BigEndian>(IndexType::ProofList as u32)
+            .unwrap();
+        buf.write_u32::<BigEndian>(8).unwrap();
+        16_u64.write::<BigEndian, _>(&mut buf);
+
+        let metadata = IndexMetadata::<u64>::from_bytes_with_order::<BigEndian>(
+            buf.into(),
+            MetadataFormat::Legacy,
+        )
+        .unwrap();
+        assert_eq!(metadata.identifier, 12);
+        assert_eq!(metadata.index_type, IndexType::ProofList);
+        assert_eq!(metadata.state, 16);
+    }
+
+    #[test]
+    fn test_byte_order_marker_round_trip() {
+        assert_eq!(Endianness::Little.to_bytes(), b"II");
+        assert_eq!(Endianness::Big.to_bytes(), b"MM");
+        assert_eq!(
+            Endianness::from_bytes(Endianness::Little.to_bytes().into()).unwrap(),
+            Endianness::Little
+        );
+        assert_eq!(
+            Endianness::from_bytes(Endianness::Big.to_bytes().into()).unwrap(),
+            Endianness::Big
+        );
+        assert_eq!(Endianness::default(), Endianness::Little);
+    }
+
+    #[test]
+    fn test_metadata_format_marker_round_trip() {
+        assert_eq!(
+            MetadataFormat::from_bytes(MetadataFormat::Legacy.to_bytes().into()).unwrap(),
+            MetadataFormat::Legacy
+        );
+        assert_eq!(
+            MetadataFormat::from_bytes(MetadataFormat::Leb128.to_bytes().into()).unwrap(),
+            MetadataFormat::Leb128
+        );
+        assert!(MetadataFormat::from_bytes(vec![2].into()).is_err());
+    }
 }